@@ -0,0 +1,1604 @@
+//! The Vulkan side of the application: instance/device setup, swapchain management, and the
+//! per-frame render loop. Everything here is owned by [`Renderer`], whose `Drop` impl tears down
+//! every handle in reverse creation order so a mid-setup `?` can never leak resources.
+
+use std::ffi::CStr;
+use std::ops::Deref;
+
+use anyhow::anyhow;
+use ash::vk;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+
+const VK_KHR_SURFACE: &CStr = cstr::cstr!("VK_KHR_surface");
+const VK_EXT_DEBUG_UTILS: &CStr = cstr::cstr!("VK_EXT_debug_utils");
+const VK_LAYER_KHRONOS_VALIDATION: &CStr = cstr::cstr!("VK_LAYER_KHRONOS_validation");
+const VK_KHR_SWAPCHAIN: &CStr = cstr::cstr!("VK_KHR_swapchain");
+
+/// Maximum number of frames that may be queued up for rendering at once.
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Message ID of `VUID-VkSwapchainCreateInfoKHR-imageExtent-01274` as reported by the
+/// validation layers. The surface extent is inherently racy while the window is being resized,
+/// so this specific message is a known false positive and gets filtered out of the log.
+const SWAPCHAIN_IMAGE_EXTENT_RACE_VUID: i32 = 0x7cd0911d;
+
+/// Whether the `VK_LAYER_KHRONOS_validation` layer and `VK_EXT_debug_utils` extension should be
+/// loaded. Defaults to on for debug builds and off for release builds, but can be overridden
+/// either way with the `VULKANTEST_VALIDATION` environment variable (`0`/`1`).
+fn validation_enabled() -> bool {
+    match std::env::var("VULKANTEST_VALIDATION") {
+        Ok(value) => value != "0",
+        Err(_) => cfg!(debug_assertions),
+    }
+}
+
+/// Thin newtype around [`ash::Instance`] that `Deref`s to the raw handle, so call sites stay
+/// ergonomic while [`Renderer`]'s `Drop` impl remains the single place that destroys it.
+struct Instance(ash::Instance);
+
+impl Deref for Instance {
+    type Target = ash::Instance;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Thin newtype around [`ash::Device`], see [`Instance`].
+struct Device(ash::Device);
+
+impl Deref for Device {
+    type Target = ash::Device;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// All of the swapchain-dependent resources, bundled together so they can be torn down and
+/// rebuilt as a unit whenever the window is resized.
+struct SwapchainResources {
+    swapchain: vk::SwapchainKHR,
+    images: Vec<vk::Image>,
+    image_views: Vec<vk::ImageView>,
+    depth_image: vk::Image,
+    depth_image_memory: vk::DeviceMemory,
+    depth_image_view: vk::ImageView,
+    framebuffers: Vec<vk::Framebuffer>,
+    extent: vk::Extent2D,
+}
+
+impl SwapchainResources {
+    /// A placeholder with every handle null, used to fill in [`AppData`] before the real
+    /// swapchain has been created so a mid-construction `?` still has something safe to destroy.
+    fn empty() -> Self {
+        Self {
+            swapchain: vk::SwapchainKHR::null(),
+            images: Vec::new(),
+            image_views: Vec::new(),
+            depth_image: vk::Image::null(),
+            depth_image_memory: vk::DeviceMemory::null(),
+            depth_image_view: vk::ImageView::null(),
+            framebuffers: Vec::new(),
+            extent: vk::Extent2D {
+                width: 0,
+                height: 0,
+            },
+        }
+    }
+
+    /// Destroys every handle in the bundle, in the reverse of creation order. Every field is
+    /// null/empty by default, so this is safe to call on an [`empty`](Self::empty) or partially
+    /// filled-in instance without checking which fields actually got created.
+    unsafe fn destroy(
+        &self,
+        device: &ash::Device,
+        swapchain_ext: &ash::extensions::khr::Swapchain,
+    ) {
+        for &framebuffer in &self.framebuffers {
+            device.destroy_framebuffer(framebuffer, None);
+        }
+        device.destroy_image_view(self.depth_image_view, None);
+        device.destroy_image(self.depth_image, None);
+        device.free_memory(self.depth_image_memory, None);
+        for &view in &self.image_views {
+            device.destroy_image_view(view, None);
+        }
+        swapchain_ext.destroy_swapchain(self.swapchain, None);
+    }
+}
+
+/// Guards the handles built up inside [`create_swapchain_resources`] so a mid-construction `?`
+/// destroys whatever was already created instead of leaking it. See [`InstanceGuard`] for the
+/// same pattern.
+struct SwapchainResourcesGuard<'a> {
+    device: &'a ash::Device,
+    swapchain_ext: &'a ash::extensions::khr::Swapchain,
+    resources: SwapchainResources,
+}
+
+impl Drop for SwapchainResourcesGuard<'_> {
+    fn drop(&mut self) {
+        unsafe { self.resources.destroy(self.device, self.swapchain_ext) };
+    }
+}
+
+impl<'a> SwapchainResourcesGuard<'a> {
+    fn new(
+        device: &'a ash::Device,
+        swapchain_ext: &'a ash::extensions::khr::Swapchain,
+    ) -> Self {
+        Self {
+            device,
+            swapchain_ext,
+            resources: SwapchainResources::empty(),
+        }
+    }
+
+    /// Takes ownership of the guarded resources without destroying them, so they can be returned
+    /// to the caller.
+    fn disarm(mut self) -> SwapchainResources {
+        std::mem::replace(&mut self.resources, SwapchainResources::empty())
+    }
+}
+
+/// A single vertex's interleaved attributes, laid out exactly as the vertex shader's `in`
+/// variables expect.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(std::mem::size_of::<Vertex>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+
+    fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+        [
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(0)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(std::mem::size_of::<[f32; 2]>() as u32)
+                .build(),
+        ]
+    }
+}
+
+const VERTICES: [Vertex; 3] = [
+    Vertex {
+        position: [0.0, -0.5],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, 0.5],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.5, 0.5],
+        color: [0.0, 0.0, 1.0],
+    },
+];
+
+/// The bag of Vulkan handles and small bits of frame state `Renderer` juggles every frame.
+/// Kept separate from `Renderer` itself so creation methods can build it up field by field.
+struct AppData {
+    surface_ext: ash::extensions::khr::Surface,
+    debug: Option<(ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT)>,
+    surface: vk::SurfaceKHR,
+    physical_device: vk::PhysicalDevice,
+    queue_family_index: u32,
+    queue: vk::Queue,
+    surface_format: vk::SurfaceFormatKHR,
+    present_mode: vk::PresentModeKHR,
+    depth_format: vk::Format,
+    swapchain_ext: ash::extensions::khr::Swapchain,
+    swapchain: SwapchainResources,
+    render_pass: vk::RenderPass,
+    pipeline_layout: vk::PipelineLayout,
+    // Bakes in the swapchain extent as a static viewport/scissor, so it has to be rebuilt
+    // alongside the swapchain on resize.
+    pipeline: vk::Pipeline,
+    vertex_buffer: vk::Buffer,
+    vertex_buffer_memory: vk::DeviceMemory,
+    command_pool: vk::CommandPool,
+    command_buffers: Vec<vk::CommandBuffer>,
+    image_available_semaphores: Vec<vk::Semaphore>,
+    render_finished_semaphores: Vec<vk::Semaphore>,
+    in_flight_fences: Vec<vk::Fence>,
+    // Tracks, for each swapchain image, the fence of the frame currently rendering into it, so
+    // that we never record commands into an image the presentation engine still owns.
+    images_in_flight: Vec<vk::Fence>,
+    current_frame: usize,
+    // Set whenever the window is resized or the presentation engine reports the swapchain is
+    // stale; consulted at the start of the next `render` call instead of recreating mid-event.
+    swapchain_dirty: bool,
+    minimized: bool,
+}
+
+/// Guards the instance, debug messenger, and surface while they're being set up, before a
+/// logical device exists to hang a full [`Renderer`] (and its `Drop` impl) off of. Destroys
+/// whatever has been filled in if dropped without [`InstanceGuard::disarm`] having been called,
+/// so a `?` partway through `Renderer::create` can't leak them.
+struct InstanceGuard {
+    instance: Option<ash::Instance>,
+    debug: Option<(ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT)>,
+    surface_ext: Option<ash::extensions::khr::Surface>,
+    surface: vk::SurfaceKHR,
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            if self.surface != vk::SurfaceKHR::null() {
+                if let Some(surface_ext) = &self.surface_ext {
+                    surface_ext.destroy_surface(self.surface, None);
+                }
+            }
+            if let Some((debug_utils_ext, debug_messenger)) = &self.debug {
+                debug_utils_ext.destroy_debug_utils_messenger(*debug_messenger, None);
+            }
+            if let Some(instance) = &self.instance {
+                instance.destroy_instance(None);
+            }
+        }
+    }
+}
+
+impl InstanceGuard {
+    fn new() -> Self {
+        Self {
+            instance: None,
+            debug: None,
+            surface_ext: None,
+            surface: vk::SurfaceKHR::null(),
+        }
+    }
+
+    /// Takes ownership of the guarded resources without destroying them, so they can be handed
+    /// off to the `Renderer` being constructed.
+    fn disarm(
+        mut self,
+    ) -> (
+        ash::Instance,
+        Option<(ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT)>,
+        ash::extensions::khr::Surface,
+        vk::SurfaceKHR,
+    ) {
+        let instance = self.instance.take().unwrap();
+        let debug = self.debug.take();
+        let surface_ext = self.surface_ext.take().unwrap();
+        let surface = self.surface;
+        self.surface = vk::SurfaceKHR::null();
+        (instance, debug, surface_ext, surface)
+    }
+}
+
+/// Owns every Vulkan handle the application uses and renders one frame at a time via
+/// [`Renderer::render`]. Dropping it waits for the device to idle and destroys everything it
+/// created, in reverse order.
+pub struct Renderer {
+    // Never read directly, but must outlive `instance`/`device`: dropping it unloads the Vulkan
+    // loader library, which would invalidate every function pointer they hold.
+    #[allow(dead_code)]
+    entry: ash::Entry,
+    instance: Instance,
+    device: Device,
+    data: AppData,
+}
+
+impl Renderer {
+    /// Brings up the full Vulkan stack for `window`: instance, debug messenger, surface, device,
+    /// swapchain, render pass and per-frame sync objects.
+    pub fn create(window: &winit::window::Window) -> anyhow::Result<Self> {
+        let entry = Self::create_entry()?;
+        let validation_enabled = validation_enabled();
+
+        // No `Device` exists yet, so there's nowhere to hang a `Renderer` (and its `Drop` impl)
+        // off of; `InstanceGuard` tears down whatever of these got created if we bail early.
+        let mut guard = InstanceGuard::new();
+        guard.instance = Some(Self::create_instance(&entry, window, validation_enabled)?);
+        let instance = guard.instance.as_ref().unwrap();
+        guard.debug = Self::create_debug_messenger(&entry, instance, validation_enabled)?;
+
+        guard.surface_ext = Some(ash::extensions::khr::Surface::new(&entry, instance));
+        guard.surface = unsafe {
+            ash_window::create_surface(
+                &entry,
+                instance,
+                window.raw_display_handle(),
+                window.raw_window_handle(),
+                None,
+            )?
+        };
+
+        let (physical_device, physical_device_properties, queue_family_index) = Self::pick_physical_device(
+            instance,
+            guard.surface_ext.as_ref().unwrap(),
+            guard.surface,
+        )?;
+        println!("Found suitable physical device: {}", unsafe {
+            CStr::from_ptr(physical_device_properties.device_name.as_ptr()).to_string_lossy()
+        });
+
+        let device = Self::create_logical_device(instance, physical_device, queue_family_index)?;
+        let queue = unsafe { device.get_device_queue(queue_family_index, 0) };
+        let swapchain_ext = ash::extensions::khr::Swapchain::new(instance, &device);
+
+        let (instance, debug, surface_ext, surface) = guard.disarm();
+
+        let window_size = window.inner_size();
+        let minimized = window_size.width == 0 || window_size.height == 0;
+
+        // From here on, `renderer` owns every handle created so far (and its `Drop` impl knows
+        // how to tear them down), so the remaining fallible steps can assign straight into
+        // `renderer.data` instead of leaking on an early `?`: a null handle or empty `Vec` is
+        // always safe to destroy.
+        let mut renderer = Self {
+            entry,
+            instance: Instance(instance),
+            device: Device(device),
+            data: AppData {
+                surface_ext,
+                debug,
+                surface,
+                physical_device,
+                queue_family_index,
+                queue,
+                surface_format: vk::SurfaceFormatKHR {
+                    format: vk::Format::UNDEFINED,
+                    color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                },
+                present_mode: vk::PresentModeKHR::FIFO,
+                depth_format: vk::Format::UNDEFINED,
+                swapchain_ext,
+                swapchain: SwapchainResources::empty(),
+                render_pass: vk::RenderPass::null(),
+                pipeline_layout: vk::PipelineLayout::null(),
+                pipeline: vk::Pipeline::null(),
+                vertex_buffer: vk::Buffer::null(),
+                vertex_buffer_memory: vk::DeviceMemory::null(),
+                command_pool: vk::CommandPool::null(),
+                command_buffers: Vec::new(),
+                image_available_semaphores: Vec::new(),
+                render_finished_semaphores: Vec::new(),
+                in_flight_fences: Vec::new(),
+                images_in_flight: Vec::new(),
+                current_frame: 0,
+                swapchain_dirty: false,
+                minimized,
+            },
+        };
+        let device = &*renderer.device;
+        let instance = &*renderer.instance;
+        let data = &mut renderer.data;
+
+        data.surface_format = Self::choose_surface_format(&data.surface_ext, physical_device, surface)?;
+        data.present_mode = Self::choose_present_mode(&data.surface_ext, physical_device, surface)?;
+        data.depth_format = Self::choose_depth_format(instance, physical_device)?;
+
+        data.render_pass = Self::create_render_pass(device, data.surface_format, data.depth_format)?;
+        data.swapchain = create_swapchain_resources(
+            instance,
+            device,
+            &data.surface_ext,
+            &data.swapchain_ext,
+            physical_device,
+            surface,
+            data.surface_format,
+            data.depth_format,
+            data.present_mode,
+            queue_family_index,
+            data.render_pass,
+            window.inner_size(),
+            vk::SwapchainKHR::null(),
+        )?;
+
+        data.pipeline_layout = Self::create_pipeline_layout(device)?;
+        data.pipeline = Self::create_pipeline(
+            device,
+            data.render_pass,
+            data.pipeline_layout,
+            data.swapchain.extent,
+        )?;
+
+        data.command_pool = Self::create_command_pool(device, queue_family_index)?;
+        data.command_buffers = Self::create_command_buffers(device, data.command_pool)?;
+
+        let (vertex_buffer, vertex_buffer_memory) =
+            Self::create_vertex_buffer(instance, device, physical_device, data.command_pool, queue)?;
+        data.vertex_buffer = vertex_buffer;
+        data.vertex_buffer_memory = vertex_buffer_memory;
+
+        let (image_available_semaphores, render_finished_semaphores, in_flight_fences) =
+            Self::create_sync_objects(device)?;
+        data.image_available_semaphores = image_available_semaphores;
+        data.render_finished_semaphores = render_finished_semaphores;
+        data.in_flight_fences = in_flight_fences;
+        data.images_in_flight = vec![vk::Fence::null(); data.swapchain.images.len()];
+
+        Ok(renderer)
+    }
+
+    fn create_entry() -> anyhow::Result<ash::Entry> {
+        unsafe {
+            ash::Entry::load_from(std::env::var("VK_LIBRARY_PATH").or_else(|err| {
+                Err(anyhow!("Error getting VK_LIBRARY_PATH env variable: {err}"))
+            })?)
+        }
+    }
+
+    fn create_instance(
+        entry: &ash::Entry,
+        window: &winit::window::Window,
+        validation_enabled: bool,
+    ) -> anyhow::Result<ash::Instance> {
+        let required_instance_extensions =
+            ash_window::enumerate_required_extensions(window.raw_display_handle())?;
+        let mut instance_extensions = required_instance_extensions.to_vec();
+        instance_extensions.push(VK_KHR_SURFACE.as_ptr());
+        if validation_enabled {
+            instance_extensions.push(VK_EXT_DEBUG_UTILS.as_ptr());
+        }
+        let layers: Vec<*const std::os::raw::c_char> = if validation_enabled {
+            vec![VK_LAYER_KHRONOS_VALIDATION.as_ptr()]
+        } else {
+            vec![]
+        };
+
+        unsafe {
+            Ok(entry.create_instance(
+                &vk::InstanceCreateInfo::builder()
+                    .application_info(
+                        &vk::ApplicationInfo::builder()
+                            .application_name(cstr::cstr!("Vulkan test"))
+                            .api_version(vk::API_VERSION_1_1),
+                    )
+                    .enabled_extension_names(&instance_extensions)
+                    .enabled_layer_names(&layers),
+                None,
+            )?)
+        }
+    }
+
+    fn create_debug_messenger(
+        entry: &ash::Entry,
+        instance: &ash::Instance,
+        validation_enabled: bool,
+    ) -> anyhow::Result<Option<(ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT)>>
+    {
+        if !validation_enabled {
+            return Ok(None);
+        }
+
+        let debug_utils_ext = ash::extensions::ext::DebugUtils::new(entry, instance);
+        let debug_messenger = unsafe {
+            debug_utils_ext.create_debug_utils_messenger(
+                &vk::DebugUtilsMessengerCreateInfoEXT::builder()
+                    .message_severity(
+                        vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+                            | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+                            | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+                            | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+                    )
+                    .message_type(
+                        vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                            | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+                            | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+                    )
+                    .pfn_user_callback(Some(logger)),
+                None,
+            )?
+        };
+        Ok(Some((debug_utils_ext, debug_messenger)))
+    }
+
+    fn pick_physical_device(
+        instance: &ash::Instance,
+        surface_ext: &ash::extensions::khr::Surface,
+        surface: vk::SurfaceKHR,
+    ) -> anyhow::Result<(vk::PhysicalDevice, vk::PhysicalDeviceProperties, u32)> {
+        let (physical_device, physical_device_properties) = unsafe {
+            instance
+                .enumerate_physical_devices()?
+                .into_iter()
+                .map(|device| (device, instance.get_physical_device_properties(device)))
+                .find(|(device, properties)| {
+                    properties.api_version >= vk::API_VERSION_1_1
+                        && surface_ext
+                            .get_physical_device_surface_formats(*device, surface)
+                            .map(|formats| !formats.is_empty())
+                            .unwrap_or(false)
+                        && surface_ext
+                            .get_physical_device_surface_present_modes(*device, surface)
+                            .map(|formats| !formats.is_empty())
+                            .unwrap_or(false)
+                })
+        }
+        .ok_or_else(|| anyhow!("Could not find suitable physical device"))?;
+
+        let queue_family_properties =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+        let queue_family_index = queue_family_properties
+            .iter()
+            .enumerate()
+            .find_map(|(idx, properties)| {
+                let idx = idx as u32;
+                let supports_present = unsafe {
+                    surface_ext
+                        .get_physical_device_surface_support(physical_device, idx, surface)
+                        .unwrap_or(false)
+                };
+                (properties.queue_flags.contains(vk::QueueFlags::GRAPHICS) && supports_present)
+                    .then(|| idx)
+            })
+            .ok_or_else(|| anyhow!("Could not find a queue family with graphics and present support in GPU chosen"))?;
+
+        Ok((physical_device, physical_device_properties, queue_family_index))
+    }
+
+    fn create_logical_device(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        queue_family_index: u32,
+    ) -> anyhow::Result<ash::Device> {
+        unsafe {
+            Ok(instance.create_device(
+                physical_device,
+                &vk::DeviceCreateInfo::builder()
+                    .queue_create_infos(&[vk::DeviceQueueCreateInfo::builder()
+                        .queue_family_index(queue_family_index)
+                        .queue_priorities(&[1.0f32])
+                        .build()])
+                    .enabled_extension_names(&[VK_KHR_SWAPCHAIN.as_ptr()]),
+                None,
+            )?)
+        }
+    }
+
+    /// Prefers the conventional `B8G8R8A8_SRGB` + `SRGB_NONLINEAR` pair sRGB-aware blitters and
+    /// swapchain presentation expect; falls back to the first format in the `SRGB_NONLINEAR`
+    /// color space if that exact pair isn't offered.
+    fn choose_surface_format(
+        surface_ext: &ash::extensions::khr::Surface,
+        physical_device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> anyhow::Result<vk::SurfaceFormatKHR> {
+        let surface_formats =
+            unsafe { surface_ext.get_physical_device_surface_formats(physical_device, surface) }?;
+        log::info!("Formats available:");
+        for format in &surface_formats {
+            log::info!(
+                "Color space: {:?}, Format: {:?}",
+                format.color_space,
+                format.format
+            );
+        }
+
+        let preferred = surface_formats.iter().find(|format| {
+            format.format == vk::Format::B8G8R8A8_SRGB
+                && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+        });
+
+        preferred
+            .or_else(|| {
+                surface_formats
+                    .iter()
+                    .find(|format| format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
+            })
+            .copied()
+            .ok_or_else(|| {
+                anyhow!("Could not find any format supported by the surface with the SRGB_NONLINEAR color space")
+            })
+    }
+
+    /// Prefers `MAILBOX` (triple buffering, no tearing, lowest latency) and falls back to `FIFO`,
+    /// which every Vulkan implementation is required to support.
+    fn choose_present_mode(
+        surface_ext: &ash::extensions::khr::Surface,
+        physical_device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> anyhow::Result<vk::PresentModeKHR> {
+        let present_modes = unsafe {
+            surface_ext.get_physical_device_surface_present_modes(physical_device, surface)?
+        };
+        Ok(if present_modes.contains(&vk::PresentModeKHR::MAILBOX) {
+            vk::PresentModeKHR::MAILBOX
+        } else {
+            vk::PresentModeKHR::FIFO
+        })
+    }
+
+    /// Probes candidate depth formats in order of preference and picks the first the physical
+    /// device supports as an optimally-tiled depth/stencil attachment.
+    fn choose_depth_format(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+    ) -> anyhow::Result<vk::Format> {
+        const CANDIDATES: [vk::Format; 3] = [
+            vk::Format::D32_SFLOAT,
+            vk::Format::D32_SFLOAT_S8_UINT,
+            vk::Format::D24_UNORM_S8_UINT,
+        ];
+        CANDIDATES
+            .into_iter()
+            .find(|&format| {
+                let properties =
+                    unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+                properties
+                    .optimal_tiling_features
+                    .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+            })
+            .ok_or_else(|| anyhow!("Could not find a supported depth/stencil format"))
+    }
+
+    /// Whether `format` carries a stencil component, i.e. whether an image view over it needs
+    /// [`vk::ImageAspectFlags::STENCIL`] in addition to [`vk::ImageAspectFlags::DEPTH`].
+    fn has_stencil_component(format: vk::Format) -> bool {
+        matches!(
+            format,
+            vk::Format::D32_SFLOAT_S8_UINT | vk::Format::D24_UNORM_S8_UINT
+        )
+    }
+
+    fn create_render_pass(
+        device: &ash::Device,
+        surface_format: vk::SurfaceFormatKHR,
+        depth_format: vk::Format,
+    ) -> anyhow::Result<vk::RenderPass> {
+        let color_attachment = vk::AttachmentDescription::builder()
+            .format(surface_format.format)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .load_op(vk::AttachmentLoadOp::CLEAR);
+
+        let depth_attachment = vk::AttachmentDescription::builder()
+            .format(depth_format)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .load_op(vk::AttachmentLoadOp::CLEAR);
+
+        let attachment_ref = &[vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build()];
+
+        let depth_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .build();
+
+        let subpass = vk::SubpassDescription::builder()
+            .color_attachments(attachment_ref)
+            .depth_stencil_attachment(&depth_attachment_ref)
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS);
+
+        let dependency = vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            )
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_stage_mask(
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            )
+            .dst_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+
+        unsafe {
+            Ok(device.create_render_pass(
+                &vk::RenderPassCreateInfo::builder()
+                    .attachments(&[color_attachment.build(), depth_attachment.build()])
+                    .subpasses(&[subpass.build()])
+                    .dependencies(&[dependency.build()]),
+                None,
+            )?)
+        }
+    }
+
+    fn create_pipeline_layout(device: &ash::Device) -> anyhow::Result<vk::PipelineLayout> {
+        unsafe {
+            Ok(device
+                .create_pipeline_layout(&vk::PipelineLayoutCreateInfo::builder(), None)?)
+        }
+    }
+
+    fn create_shader_module(
+        device: &ash::Device,
+        bytes: &[u8],
+    ) -> anyhow::Result<vk::ShaderModule> {
+        let code = ash::util::read_spv(&mut std::io::Cursor::new(bytes))?;
+        unsafe {
+            Ok(device
+                .create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(&code), None)?)
+        }
+    }
+
+    /// Builds the hello-triangle pipeline: vertex input matching [`Vertex`], a viewport/scissor
+    /// baked in at `extent`, and the existing render pass's single color attachment with
+    /// blending disabled.
+    fn create_pipeline(
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+        pipeline_layout: vk::PipelineLayout,
+        extent: vk::Extent2D,
+    ) -> anyhow::Result<vk::Pipeline> {
+        let vert_module = Self::create_shader_module(
+            device,
+            include_bytes!("../shaders/triangle.vert.spv"),
+        )?;
+        let frag_module = Self::create_shader_module(
+            device,
+            include_bytes!("../shaders/triangle.frag.spv"),
+        )?;
+
+        let entry_point = cstr::cstr!("main");
+        let stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point)
+                .build(),
+        ];
+
+        let binding_descriptions = [Vertex::binding_description()];
+        let attribute_descriptions = Vertex::attribute_descriptions();
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&binding_descriptions)
+            .vertex_attribute_descriptions(&attribute_descriptions);
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        let viewports = [vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }];
+        let scissors = [vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        }];
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewports(&viewports)
+            .scissors(&scissors);
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::BACK)
+            .front_face(vk::FrontFace::CLOCKWISE)
+            .depth_bias_enable(false);
+
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+            .sample_shading_enable(false)
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false)
+            .build();
+        let color_blend_attachments = [color_blend_attachment];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .attachments(&color_blend_attachments);
+
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(vk::CompareOp::LESS)
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(false);
+
+        let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&depth_stencil_state)
+            .color_blend_state(&color_blend_state)
+            .layout(pipeline_layout)
+            .render_pass(render_pass)
+            .subpass(0);
+
+        let result = unsafe {
+            device
+                .create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info.build()], None)
+                .map(|pipelines| pipelines[0])
+                .map_err(|(_, err)| anyhow!("Failed to create graphics pipeline: {err}"))
+        };
+
+        unsafe {
+            device.destroy_shader_module(vert_module, None);
+            device.destroy_shader_module(frag_module, None);
+        }
+
+        result
+    }
+
+    fn create_command_pool(
+        device: &ash::Device,
+        queue_family_index: u32,
+    ) -> anyhow::Result<vk::CommandPool> {
+        unsafe {
+            Ok(device.create_command_pool(
+                &vk::CommandPoolCreateInfo::builder()
+                    .queue_family_index(queue_family_index)
+                    .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER), // Allow resetting command buffers individually
+                None,
+            )?)
+        }
+    }
+
+    fn create_command_buffers(
+        device: &ash::Device,
+        command_pool: vk::CommandPool,
+    ) -> anyhow::Result<Vec<vk::CommandBuffer>> {
+        unsafe {
+            Ok(device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::builder()
+                    .command_buffer_count(MAX_FRAMES_IN_FLIGHT as u32)
+                    .command_pool(command_pool)
+                    .level(vk::CommandBufferLevel::PRIMARY),
+            )?)
+        }
+    }
+
+    fn create_sync_objects(
+        device: &ash::Device,
+    ) -> anyhow::Result<(Vec<vk::Semaphore>, Vec<vk::Semaphore>, Vec<vk::Fence>)> {
+        let semaphore_info = vk::SemaphoreCreateInfo::builder();
+        let signaled_fence_info =
+            vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+
+        let mut image_available_semaphores = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        let mut render_finished_semaphores = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        let mut in_flight_fences = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            unsafe {
+                image_available_semaphores.push(device.create_semaphore(&semaphore_info, None)?);
+                render_finished_semaphores.push(device.create_semaphore(&semaphore_info, None)?);
+                in_flight_fences.push(device.create_fence(&signaled_fence_info, None)?);
+            }
+        }
+
+        Ok((
+            image_available_semaphores,
+            render_finished_semaphores,
+            in_flight_fences,
+        ))
+    }
+
+    /// Scans the physical device's memory types for one whose bit is set in `type_filter` (as
+    /// returned by `get_buffer_memory_requirements`) and that offers every flag in `properties`.
+    fn find_memory_type(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        type_filter: u32,
+        properties: vk::MemoryPropertyFlags,
+    ) -> anyhow::Result<u32> {
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                type_filter & (1 << i) != 0
+                    && memory_properties.memory_types[i as usize]
+                        .property_flags
+                        .contains(properties)
+            })
+            .ok_or_else(|| anyhow!("Could not find a suitable memory type"))
+    }
+
+    /// Allocates a buffer and memory satisfying `properties`, then binds them together. Does not
+    /// populate the memory; callers map it themselves (for host-visible buffers) or fill it via a
+    /// device-side copy (for device-local ones).
+    fn create_buffer(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+    ) -> anyhow::Result<(vk::Buffer, vk::DeviceMemory)> {
+        let buffer = unsafe {
+            device.create_buffer(
+                &vk::BufferCreateInfo::builder()
+                    .size(size)
+                    .usage(usage)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                None,
+            )?
+        };
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let memory_type_index = Self::find_memory_type(
+            instance,
+            physical_device,
+            requirements.memory_type_bits,
+            properties,
+        )?;
+        let memory = unsafe {
+            device.allocate_memory(
+                &vk::MemoryAllocateInfo::builder()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                None,
+            )?
+        };
+        unsafe { device.bind_buffer_memory(buffer, memory, 0)? };
+
+        Ok((buffer, memory))
+    }
+
+    /// Records, submits, and waits on a single-use command buffer copying `size` bytes from `src`
+    /// to `dst`. Used to move data from the host-visible staging buffer into device-local memory.
+    fn copy_buffer(
+        device: &ash::Device,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        src: vk::Buffer,
+        dst: vk::Buffer,
+        size: vk::DeviceSize,
+    ) -> anyhow::Result<()> {
+        let command_buffer = unsafe {
+            device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::builder()
+                    .command_pool(command_pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )?[0]
+        };
+
+        unsafe {
+            device.begin_command_buffer(
+                command_buffer,
+                &vk::CommandBufferBeginInfo::builder()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+            device.cmd_copy_buffer(
+                command_buffer,
+                src,
+                dst,
+                &[vk::BufferCopy::builder().size(size).build()],
+            );
+            device.end_command_buffer(command_buffer)?;
+
+            let command_buffers = [command_buffer];
+            let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+            device.queue_submit(queue, &[submit_info.build()], vk::Fence::null())?;
+            device.queue_wait_idle(queue)?;
+
+            device.free_command_buffers(command_pool, &command_buffers);
+        }
+
+        Ok(())
+    }
+
+    /// Uploads [`VERTICES`] into a device-local vertex buffer via a temporary host-visible
+    /// staging buffer, which is destroyed once the copy completes.
+    fn create_vertex_buffer(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> anyhow::Result<(vk::Buffer, vk::DeviceMemory)> {
+        let size = std::mem::size_of_val(&VERTICES) as vk::DeviceSize;
+
+        let (staging_buffer, staging_memory) = Self::create_buffer(
+            instance,
+            device,
+            physical_device,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        unsafe {
+            let data = device.map_memory(staging_memory, 0, size, vk::MemoryMapFlags::empty())?;
+            std::ptr::copy_nonoverlapping(VERTICES.as_ptr(), data.cast(), VERTICES.len());
+            device.unmap_memory(staging_memory);
+        }
+
+        let result = Self::create_buffer(
+            instance,
+            device,
+            physical_device,
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+        .and_then(|(vertex_buffer, vertex_buffer_memory)| {
+            match Self::copy_buffer(
+                device,
+                command_pool,
+                queue,
+                staging_buffer,
+                vertex_buffer,
+                size,
+            ) {
+                Ok(()) => Ok((vertex_buffer, vertex_buffer_memory)),
+                Err(e) => {
+                    unsafe {
+                        device.destroy_buffer(vertex_buffer, None);
+                        device.free_memory(vertex_buffer_memory, None);
+                    }
+                    Err(e)
+                }
+            }
+        });
+
+        unsafe {
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_memory, None);
+        }
+
+        result
+    }
+
+    /// Records and submits a clear-and-present frame, recreating the swapchain first if it was
+    /// marked stale by a resize or by the presentation engine. Does nothing while minimized.
+    pub fn render(&mut self, window: &winit::window::Window) -> anyhow::Result<()> {
+        if self.data.minimized {
+            return Ok(());
+        }
+
+        if self.data.swapchain_dirty {
+            self.recreate_swapchain(window)?;
+        }
+
+        let data = &mut self.data;
+        let device = &*self.device;
+
+        let in_flight_fence = data.in_flight_fences[data.current_frame];
+        unsafe { device.wait_for_fences(&[in_flight_fence], true, u64::MAX)? };
+
+        let image_available_semaphore = data.image_available_semaphores[data.current_frame];
+        let image_index = match unsafe {
+            data.swapchain_ext.acquire_next_image(
+                data.swapchain.swapchain,
+                u64::MAX,
+                image_available_semaphore,
+                vk::Fence::null(),
+            )
+        } {
+            Ok((image_index, suboptimal)) => {
+                if suboptimal {
+                    data.swapchain_dirty = true;
+                }
+                image_index as usize
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                data.swapchain_dirty = true;
+                return Ok(());
+            }
+            Err(err) => return Err(anyhow!("Failed to acquire next swapchain image: {err}")),
+        };
+
+        let image_in_flight = data.images_in_flight[image_index];
+        if image_in_flight != vk::Fence::null() {
+            unsafe { device.wait_for_fences(&[image_in_flight], true, u64::MAX)? };
+        }
+        data.images_in_flight[image_index] = in_flight_fence;
+
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 1.0],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+
+        let command_buffer = data.command_buffers[data.current_frame];
+        unsafe {
+            device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())?;
+            device.begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::builder())?;
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &vk::RenderPassBeginInfo::builder()
+                    .render_pass(data.render_pass)
+                    .framebuffer(data.swapchain.framebuffers[image_index])
+                    .render_area(vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent: data.swapchain.extent,
+                    })
+                    .clear_values(&clear_values),
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.pipeline,
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[data.vertex_buffer], &[0]);
+            device.cmd_draw(command_buffer, VERTICES.len() as u32, 1, 0, 0);
+            device.cmd_end_render_pass(command_buffer);
+            device.end_command_buffer(command_buffer)?;
+        }
+
+        let render_finished_semaphore = data.render_finished_semaphores[data.current_frame];
+        let wait_semaphores = [image_available_semaphore];
+        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let signal_semaphores = [render_finished_semaphore];
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores);
+
+        unsafe {
+            device.reset_fences(&[in_flight_fence])?;
+            device.queue_submit(data.queue, &[submit_info.build()], in_flight_fence)?;
+        }
+
+        let swapchains = [data.swapchain.swapchain];
+        let image_indices = [image_index as u32];
+        let present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(&signal_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+
+        match unsafe { data.swapchain_ext.queue_present(data.queue, &present_info) } {
+            Ok(suboptimal) if suboptimal => data.swapchain_dirty = true,
+            Ok(_) => (),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => data.swapchain_dirty = true,
+            Err(err) => return Err(anyhow!("Failed to present swapchain image: {err}")),
+        }
+
+        data.current_frame = (data.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+
+        Ok(())
+    }
+
+    /// Notifies the renderer that the window changed size, so the next [`Renderer::render`] call
+    /// recreates the swapchain (or skips rendering entirely while minimized).
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.data.minimized = new_size.width == 0 || new_size.height == 0;
+        self.data.swapchain_dirty = true;
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.data.minimized
+    }
+
+    fn recreate_swapchain(&mut self, window: &winit::window::Window) -> anyhow::Result<()> {
+        let data = &mut self.data;
+        let device = &*self.device;
+        let instance = &*self.instance;
+
+        unsafe { device.device_wait_idle()? };
+
+        let old_swapchain = data.swapchain.swapchain;
+        let new_swapchain = create_swapchain_resources(
+            instance,
+            device,
+            &data.surface_ext,
+            &data.swapchain_ext,
+            data.physical_device,
+            data.surface,
+            data.surface_format,
+            data.depth_format,
+            data.present_mode,
+            data.queue_family_index,
+            data.render_pass,
+            window.inner_size(),
+            old_swapchain,
+        )?;
+        // If this fails, `new_swapchain` is otherwise fully built and nothing else references it
+        // yet, so it has to be torn down here instead of leaking.
+        let new_pipeline = match Self::create_pipeline(
+            device,
+            data.render_pass,
+            data.pipeline_layout,
+            new_swapchain.extent,
+        ) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                unsafe { new_swapchain.destroy(device, &data.swapchain_ext) };
+                return Err(err);
+            }
+        };
+
+        // Only tear down the old resources once their replacements above are confirmed built: if
+        // either `?` above had fired after we'd already destroyed these, `data.swapchain`/
+        // `data.pipeline` would still reference them, and `Renderer::drop` would later destroy
+        // them a second time.
+        for &framebuffer in &data.swapchain.framebuffers {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+        for &view in &data.swapchain.image_views {
+            unsafe { device.destroy_image_view(view, None) };
+        }
+        unsafe {
+            device.destroy_image_view(data.swapchain.depth_image_view, None);
+            device.destroy_image(data.swapchain.depth_image, None);
+            device.free_memory(data.swapchain.depth_image_memory, None);
+            device.destroy_pipeline(data.pipeline, None);
+            data.swapchain_ext.destroy_swapchain(old_swapchain, None);
+        }
+
+        data.images_in_flight = vec![vk::Fence::null(); new_swapchain.images.len()];
+        data.swapchain = new_swapchain;
+        data.pipeline = new_pipeline;
+        data.swapchain_dirty = false;
+
+        Ok(())
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        let device = &*self.device;
+        unsafe {
+            let _ = device.device_wait_idle();
+
+            for &semaphore in &self.data.image_available_semaphores {
+                device.destroy_semaphore(semaphore, None);
+            }
+            for &semaphore in &self.data.render_finished_semaphores {
+                device.destroy_semaphore(semaphore, None);
+            }
+            for &fence in &self.data.in_flight_fences {
+                device.destroy_fence(fence, None);
+            }
+            for &framebuffer in &self.data.swapchain.framebuffers {
+                device.destroy_framebuffer(framebuffer, None);
+            }
+            device.destroy_pipeline(self.data.pipeline, None);
+            device.destroy_pipeline_layout(self.data.pipeline_layout, None);
+            device.destroy_buffer(self.data.vertex_buffer, None);
+            device.free_memory(self.data.vertex_buffer_memory, None);
+            device.destroy_render_pass(self.data.render_pass, None);
+            for &view in &self.data.swapchain.image_views {
+                device.destroy_image_view(view, None);
+            }
+            device.destroy_image_view(self.data.swapchain.depth_image_view, None);
+            device.destroy_image(self.data.swapchain.depth_image, None);
+            device.free_memory(self.data.swapchain.depth_image_memory, None);
+            device.destroy_command_pool(self.data.command_pool, None);
+            self.data
+                .swapchain_ext
+                .destroy_swapchain(self.data.swapchain.swapchain, None);
+            device.destroy_device(None);
+            self.data.surface_ext.destroy_surface(self.data.surface, None);
+            if let Some((debug_utils_ext, debug_messenger)) = &self.data.debug {
+                debug_utils_ext.destroy_debug_utils_messenger(*debug_messenger, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+/// Clamps the window size to the extent the surface is actually willing to accept. Most
+/// platforms report `current_extent` directly; the `u32::MAX` sentinel means the surface instead
+/// defers to whatever extent we ask for, bounded by `min_image_extent`/`max_image_extent`.
+fn choose_swap_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    window_size: winit::dpi::PhysicalSize<u32>,
+) -> vk::Extent2D {
+    if capabilities.current_extent.width != u32::MAX {
+        capabilities.current_extent
+    } else {
+        vk::Extent2D {
+            width: window_size.width.clamp(
+                capabilities.min_image_extent.width,
+                capabilities.max_image_extent.width,
+            ),
+            height: window_size.height.clamp(
+                capabilities.min_image_extent.height,
+                capabilities.max_image_extent.height,
+            ),
+        }
+    }
+}
+
+/// Builds a swapchain plus the image views and framebuffers that depend on its extent and
+/// images. Pass the previous swapchain as `old_swapchain` (instead of `null()`) when recreating
+/// it after a resize so the driver can hand over presentation more smoothly; the caller is still
+/// responsible for destroying `old_swapchain` once this returns.
+fn create_swapchain_resources(
+    instance: &ash::Instance,
+    device: &ash::Device,
+    surface_ext: &ash::extensions::khr::Surface,
+    swapchain_ext: &ash::extensions::khr::Swapchain,
+    physical_device: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    surface_format: vk::SurfaceFormatKHR,
+    depth_format: vk::Format,
+    present_mode: vk::PresentModeKHR,
+    queue_family_index: u32,
+    render_pass: vk::RenderPass,
+    window_size: winit::dpi::PhysicalSize<u32>,
+    old_swapchain: vk::SwapchainKHR,
+) -> anyhow::Result<SwapchainResources> {
+    let capabilities = unsafe {
+        surface_ext.get_physical_device_surface_capabilities(physical_device, surface)?
+    };
+    // Request one more than the minimum to avoid stalling on the driver while it's still
+    // processing the previous image, clamped to `max_image_count` (0 means "no limit").
+    let min_image_count = if capabilities.max_image_count == 0 {
+        capabilities.min_image_count + 1
+    } else {
+        (capabilities.min_image_count + 1).min(capabilities.max_image_count)
+    };
+    let extent = choose_swap_extent(&capabilities, window_size);
+
+    // From here on, every fallible step fills in `guard.resources` directly: if a later `?`
+    // fires, the guard's `Drop` impl destroys whatever got created instead of leaking it.
+    let mut guard = SwapchainResourcesGuard::new(device, swapchain_ext);
+    guard.resources.extent = extent;
+
+    guard.resources.swapchain = unsafe {
+        swapchain_ext.create_swapchain(
+            &vk::SwapchainCreateInfoKHR::builder()
+                .clipped(true)
+                .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE) // TODO: Use POST_MULTIPLIED when available
+                .flags(vk::SwapchainCreateFlagsKHR::default())
+                .image_array_layers(1)
+                .image_color_space(surface_format.color_space)
+                .image_extent(extent)
+                .image_format(surface_format.format)
+                .image_sharing_mode(vk::SharingMode::EXCLUSIVE) // Only one queue can access the swapchain at a time
+                .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+                .min_image_count(min_image_count)
+                .old_swapchain(old_swapchain)
+                .pre_transform(capabilities.current_transform)
+                .present_mode(present_mode)
+                .queue_family_indices(&[queue_family_index])
+                .surface(surface),
+            None,
+        )?
+    };
+
+    guard.resources.images =
+        unsafe { swapchain_ext.get_swapchain_images(guard.resources.swapchain)? };
+    guard.resources.image_views = guard
+        .resources
+        .images
+        .iter()
+        .map(|image| unsafe {
+            device.create_image_view(
+                &vk::ImageViewCreateInfo::builder()
+                    .image(*image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(surface_format.format)
+                    .components(vk::ComponentMapping::default())
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    }),
+                None,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    guard.resources.depth_image = unsafe {
+        device.create_image(
+            &vk::ImageCreateInfo::builder()
+                .image_type(vk::ImageType::TYPE_2D)
+                .extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .format(depth_format)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE),
+            None,
+        )?
+    };
+    let depth_requirements =
+        unsafe { device.get_image_memory_requirements(guard.resources.depth_image) };
+    let depth_memory_type_index = Renderer::find_memory_type(
+        instance,
+        physical_device,
+        depth_requirements.memory_type_bits,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+    guard.resources.depth_image_memory = unsafe {
+        device.allocate_memory(
+            &vk::MemoryAllocateInfo::builder()
+                .allocation_size(depth_requirements.size)
+                .memory_type_index(depth_memory_type_index),
+            None,
+        )?
+    };
+    unsafe {
+        device.bind_image_memory(
+            guard.resources.depth_image,
+            guard.resources.depth_image_memory,
+            0,
+        )?
+    };
+    let mut depth_aspect_mask = vk::ImageAspectFlags::DEPTH;
+    if Renderer::has_stencil_component(depth_format) {
+        depth_aspect_mask |= vk::ImageAspectFlags::STENCIL;
+    }
+    guard.resources.depth_image_view = unsafe {
+        device.create_image_view(
+            &vk::ImageViewCreateInfo::builder()
+                .image(guard.resources.depth_image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(depth_format)
+                .components(vk::ComponentMapping::default())
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: depth_aspect_mask,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }),
+            None,
+        )?
+    };
+
+    guard.resources.framebuffers = guard
+        .resources
+        .image_views
+        .iter()
+        .map(|view| unsafe {
+            device.create_framebuffer(
+                &vk::FramebufferCreateInfo::builder()
+                    .render_pass(render_pass)
+                    .attachments(&[*view, guard.resources.depth_image_view])
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1),
+                None,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(guard.disarm())
+}
+
+unsafe extern "system" fn logger(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    ty: vk::DebugUtilsMessageTypeFlagsEXT,
+    data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _: *mut std::os::raw::c_void,
+) -> vk::Bool32 {
+    // Must never unwind across the FFI boundary into the Vulkan loader.
+    if std::panic::catch_unwind(|| unsafe { log_debug_utils_message(severity, ty, data) }).is_err()
+    {
+        eprintln!("panic in Vulkan debug callback");
+    }
+    vk::FALSE
+}
+
+/// Logs a single debug-utils callback invocation: severity (via `>=` ordering, so a combination
+/// of severity bits is still handled sensibly), message type, the Vulkan-assigned message ID, and
+/// any object/queue/command-buffer labels the driver attached to the message.
+unsafe fn log_debug_utils_message(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    ty: vk::DebugUtilsMessageTypeFlagsEXT,
+    data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+) {
+    let data = &*data;
+
+    if data.message_id_number == SWAPCHAIN_IMAGE_EXTENT_RACE_VUID {
+        return;
+    }
+
+    let level = if severity.as_raw() >= vk::DebugUtilsMessageSeverityFlagsEXT::ERROR.as_raw() {
+        log::Level::Error
+    } else if severity.as_raw() >= vk::DebugUtilsMessageSeverityFlagsEXT::WARNING.as_raw() {
+        log::Level::Warn
+    } else if severity.as_raw() >= vk::DebugUtilsMessageSeverityFlagsEXT::INFO.as_raw() {
+        log::Level::Info
+    } else {
+        log::Level::Debug
+    };
+
+    let message_id_name = if data.p_message_id_name.is_null() {
+        "<no message id name>".into()
+    } else {
+        CStr::from_ptr(data.p_message_id_name).to_string_lossy()
+    };
+    let message = if data.p_message.is_null() {
+        "<no message>".into()
+    } else {
+        CStr::from_ptr(data.p_message).to_string_lossy()
+    };
+
+    log::log!(
+        level,
+        "[{message_id_name} ({})] ({ty:?}) {message}",
+        data.message_id_number
+    );
+
+    for object in raw_parts(data.p_objects, data.object_count) {
+        let name = if object.p_object_name.is_null() {
+            "<unnamed>".into()
+        } else {
+            CStr::from_ptr(object.p_object_name).to_string_lossy()
+        };
+        log::log!(
+            level,
+            "  object: {:?} {:#x} ({name})",
+            object.object_type,
+            object.object_handle
+        );
+    }
+
+    for label in raw_parts(data.p_queue_labels, data.queue_label_count) {
+        log::log!(level, "  queue label: {}", label_name(label));
+    }
+    for label in raw_parts(data.p_cmd_buf_labels, data.cmd_buf_label_count) {
+        log::log!(level, "  command buffer label: {}", label_name(label));
+    }
+}
+
+/// `std::slice::from_raw_parts`, but tolerates the null+zero-length pointers the debug-utils
+/// callback data uses when a given label/object array is absent.
+unsafe fn raw_parts<'a, T>(ptr: *const T, count: u32) -> &'a [T] {
+    if ptr.is_null() || count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, count as usize)
+    }
+}
+
+unsafe fn label_name(label: &vk::DebugUtilsLabelEXT) -> std::borrow::Cow<'static, str> {
+    if label.p_label_name.is_null() {
+        "<unnamed>".into()
+    } else {
+        CStr::from_ptr(label.p_label_name)
+            .to_string_lossy()
+            .into_owned()
+            .into()
+    }
+}